@@ -0,0 +1,264 @@
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use twox_hash::XxHash64;
+
+use crate::format::Format;
+use crate::resize::{AspectRatio, ResizeOp};
+use crate::{BackgroundColor, ConversionMode};
+
+/// 出力ファイル名に埋め込むハッシュの元になる設定値
+pub struct Settings {
+    pub mode: ConversionMode,
+    pub aspect_ratio: AspectRatio,
+    pub bg_color: BackgroundColor,
+    pub resize: Option<ResizeOp>,
+    pub quality: u8,
+    pub max_size: usize,
+    pub format: Format,
+}
+
+impl Settings {
+    /// ハッシュ計算用にすべての設定値をバイト列へ直列化する
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.push(self.mode as u8);
+        buf.extend_from_slice(&self.aspect_ratio.w.to_le_bytes());
+        buf.extend_from_slice(&self.aspect_ratio.h.to_le_bytes());
+        buf.push(self.bg_color as u8);
+
+        match self.resize {
+            None => buf.push(0),
+            Some(ResizeOp::Scale(w, h)) => {
+                buf.push(1);
+                buf.extend_from_slice(&w.to_le_bytes());
+                buf.extend_from_slice(&h.to_le_bytes());
+            }
+            Some(ResizeOp::FitWidth(w)) => {
+                buf.push(2);
+                buf.extend_from_slice(&w.to_le_bytes());
+            }
+            Some(ResizeOp::FitHeight(h)) => {
+                buf.push(3);
+                buf.extend_from_slice(&h.to_le_bytes());
+            }
+            Some(ResizeOp::Fit(w, h)) => {
+                buf.push(4);
+                buf.extend_from_slice(&w.to_le_bytes());
+                buf.extend_from_slice(&h.to_le_bytes());
+            }
+            Some(ResizeOp::Fill(w, h)) => {
+                buf.push(5);
+                buf.extend_from_slice(&w.to_le_bytes());
+                buf.extend_from_slice(&h.to_le_bytes());
+            }
+        }
+
+        buf.push(self.quality);
+        buf.extend_from_slice(&self.max_size.to_le_bytes());
+
+        match self.format {
+            Format::Jpeg(q) => {
+                buf.push(0);
+                buf.push(q);
+            }
+            Format::Png => buf.push(1),
+            Format::WebP { quality } => {
+                buf.push(2);
+                buf.push(quality);
+            }
+            Format::Avif { quality, speed } => {
+                buf.push(3);
+                buf.push(quality);
+                buf.push(speed);
+            }
+        }
+
+        buf
+    }
+
+    /// 出力ファイル名のハッシュ16桁に続く2桁に埋め込む識別バイト (フォーマット種別)
+    fn opt_byte(&self) -> u8 {
+        match self.format {
+            Format::Jpeg(_) => 0,
+            Format::Png => 1,
+            Format::WebP { .. } => 2,
+            Format::Avif { .. } => 3,
+        }
+    }
+}
+
+/// 入力バイト列と設定から算出したキャッシュキー
+#[derive(Debug, Clone, Copy)]
+pub struct CacheKey {
+    hash: u64,
+    opt: u8,
+}
+
+impl CacheKey {
+    /// ファイル名に埋め込む18桁の16進タグ (ハッシュ16桁 + 識別バイト2桁)
+    fn tag(&self) -> String {
+        format!("{:016x}{:02x}", self.hash, self.opt)
+    }
+}
+
+/// 入力ファイルのバイト列と設定からキャッシュキーを算出する
+pub fn compute_key(input_bytes: &[u8], settings: &Settings) -> CacheKey {
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(input_bytes);
+    hasher.write(&settings.to_bytes());
+
+    CacheKey {
+        hash: hasher.finish(),
+        opt: settings.opt_byte(),
+    }
+}
+
+/// キャッシュキーを埋め込んだ出力パスを生成する (例: `photo.jpg.89ab...1f4200.jpg`)
+///
+/// 入力の拡張子 (`source_ext`) をファイル名に含めることで、stemが同じでも
+/// 拡張子が異なる入力 (例: `photo.jpg` と `photo.png`) のキャッシュ出力が
+/// 互いに衝突・干渉しないようにする
+pub fn cached_output_path(
+    parent: &Path,
+    stem: &str,
+    source_ext: &str,
+    key: CacheKey,
+    extension: &str,
+) -> PathBuf {
+    parent.join(format!("{}.{}.{}.{}", stem, source_ext, key.tag(), extension))
+}
+
+/// 同じstem・同じ入力拡張子を持つキャッシュ出力にマッチする正規表現を組み立てる
+fn stale_pattern(stem: &str, source_ext: &str) -> Result<Regex> {
+    let pattern = format!(
+        r"^{}\.{}\.[0-9a-f]{{16}}[0-9a-f]{{2}}\.[A-Za-z0-9]+$",
+        regex::escape(stem),
+        regex::escape(source_ext)
+    );
+    Regex::new(&pattern).context("Failed to build cache file pattern")
+}
+
+/// `<stem>.<source_ext>.<16桁ハッシュ><2桁識別子>.<拡張子>` 形式の
+/// キャッシュ出力ファイル名にマッチする (stemを問わない汎用版)
+fn generic_cache_pattern() -> Result<Regex> {
+    Regex::new(r"^.+\.[A-Za-z0-9]+\.[0-9a-f]{16}[0-9a-f]{2}\.[A-Za-z0-9]+$")
+        .context("Failed to build generic cache file pattern")
+}
+
+/// このパスが (このツール自身が書き出した) キャッシュ出力ファイルかどうか
+///
+/// ディレクトリ走査で入力ファイルを集める際、前回実行で生成したキャッシュ出力を
+/// 新たな入力として拾い直し、際限なく世代を重ねて再エンコードしてしまうのを防ぐために使う
+pub fn is_cache_output(path: &Path) -> bool {
+    let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+
+    generic_cache_pattern()
+        .map(|pattern| pattern.is_match(file_name))
+        .unwrap_or(false)
+}
+
+/// 現在の設定と一致しない、同じ入力ファイル (stem + 拡張子) の古いキャッシュ出力を削除する
+///
+/// stemだけでなく入力拡張子も一致させることで、`photo.jpg` の処理が
+/// `photo.png` の有効なキャッシュ出力を誤って削除しないようにする
+pub fn sweep_stale_outputs(
+    parent: &Path,
+    stem: &str,
+    source_ext: &str,
+    current_output: &Path,
+) -> Result<usize> {
+    let pattern = stale_pattern(stem, source_ext)?;
+    let mut removed = 0;
+
+    for entry in fs::read_dir(parent)
+        .with_context(|| format!("Failed to read directory: {}", parent.display()))?
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if path == current_output {
+            continue;
+        }
+
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+
+        if pattern.is_match(file_name) {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove stale cache file: {}", path.display()))?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resize::AspectRatio;
+    use crate::{BackgroundColor, ConversionMode};
+
+    fn settings() -> Settings {
+        Settings {
+            mode: ConversionMode::Crop,
+            aspect_ratio: AspectRatio { w: 4, h: 5 },
+            bg_color: BackgroundColor::White,
+            resize: None,
+            quality: 90,
+            max_size: 8,
+            format: Format::Jpeg(90),
+        }
+    }
+
+    #[test]
+    fn compute_key_is_deterministic() {
+        let bytes = b"some input bytes";
+        let a = compute_key(bytes, &settings());
+        let b = compute_key(bytes, &settings());
+        assert_eq!(a.hash, b.hash);
+        assert_eq!(a.opt, b.opt);
+    }
+
+    #[test]
+    fn compute_key_changes_with_input_bytes() {
+        let a = compute_key(b"photo-a", &settings());
+        let b = compute_key(b"photo-b", &settings());
+        assert_ne!(a.hash, b.hash);
+    }
+
+    #[test]
+    fn cached_output_path_is_recognized_as_cache_output() {
+        let key = compute_key(b"input", &settings());
+        let path = cached_output_path(Path::new("/photos"), "beach", "jpg", key, "jpg");
+
+        assert!(is_cache_output(&path));
+    }
+
+    #[test]
+    fn is_cache_output_rejects_plain_source_images() {
+        assert!(!is_cache_output(Path::new("/photos/beach.jpg")));
+        assert!(!is_cache_output(Path::new("/photos/beach.png")));
+    }
+
+    #[test]
+    fn stale_pattern_matches_only_same_stem_and_source_ext() {
+        let key = compute_key(b"input", &settings());
+        let pattern = stale_pattern("beach", "jpg").unwrap();
+
+        let ours = cached_output_path(Path::new("/photos"), "beach", "jpg", key, "jpg");
+        let other_stem = cached_output_path(Path::new("/photos"), "sunset", "jpg", key, "jpg");
+        let other_source_ext = cached_output_path(Path::new("/photos"), "beach", "png", key, "jpg");
+
+        assert!(pattern.is_match(ours.file_name().unwrap().to_str().unwrap()));
+        assert!(!pattern.is_match(other_stem.file_name().unwrap().to_str().unwrap()));
+        assert!(!pattern.is_match(other_source_ext.file_name().unwrap().to_str().unwrap()));
+    }
+}
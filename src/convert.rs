@@ -0,0 +1,222 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, ImageFormat, RgbaImage};
+
+/// `image` クレートが扱える全フォーマット (SVGはラスタライズ専用の入力形式)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormatExt {
+    Jpeg,
+    Png,
+    WebP,
+    Bmp,
+    Tiff,
+    Gif,
+    Tga,
+    Ico,
+    Hdr,
+    OpenExr,
+    Farbfeld,
+    Svg,
+}
+
+impl ImageFormatExt {
+    /// 対応する全フォーマット (表示・一覧用)
+    pub const ALL: &'static [ImageFormatExt] = &[
+        ImageFormatExt::Jpeg,
+        ImageFormatExt::Png,
+        ImageFormatExt::WebP,
+        ImageFormatExt::Bmp,
+        ImageFormatExt::Tiff,
+        ImageFormatExt::Gif,
+        ImageFormatExt::Tga,
+        ImageFormatExt::Ico,
+        ImageFormatExt::Hdr,
+        ImageFormatExt::OpenExr,
+        ImageFormatExt::Farbfeld,
+        ImageFormatExt::Svg,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormatExt::Jpeg => "jpg",
+            ImageFormatExt::Png => "png",
+            ImageFormatExt::WebP => "webp",
+            ImageFormatExt::Bmp => "bmp",
+            ImageFormatExt::Tiff => "tiff",
+            ImageFormatExt::Gif => "gif",
+            ImageFormatExt::Tga => "tga",
+            ImageFormatExt::Ico => "ico",
+            ImageFormatExt::Hdr => "hdr",
+            ImageFormatExt::OpenExr => "exr",
+            ImageFormatExt::Farbfeld => "ff",
+            ImageFormatExt::Svg => "svg",
+        }
+    }
+
+    /// 出力 (エンコード) 先として使用できるか。SVGはデコード専用。
+    pub fn supports_encode(&self) -> bool {
+        !matches!(self, ImageFormatExt::Svg)
+    }
+
+    fn image_format(&self) -> Option<ImageFormat> {
+        match self {
+            ImageFormatExt::Jpeg => Some(ImageFormat::Jpeg),
+            ImageFormatExt::Png => Some(ImageFormat::Png),
+            ImageFormatExt::WebP => Some(ImageFormat::WebP),
+            ImageFormatExt::Bmp => Some(ImageFormat::Bmp),
+            ImageFormatExt::Tiff => Some(ImageFormat::Tiff),
+            ImageFormatExt::Gif => Some(ImageFormat::Gif),
+            ImageFormatExt::Tga => Some(ImageFormat::Tga),
+            ImageFormatExt::Ico => Some(ImageFormat::Ico),
+            ImageFormatExt::Hdr => Some(ImageFormat::Hdr),
+            ImageFormatExt::OpenExr => Some(ImageFormat::OpenExr),
+            ImageFormatExt::Farbfeld => Some(ImageFormat::Farbfeld),
+            ImageFormatExt::Svg => None,
+        }
+    }
+}
+
+impl FromStr for ImageFormatExt {
+    type Err = String;
+
+    fn from_str(ext: &str) -> Result<Self, Self::Err> {
+        match ext.to_lowercase().as_str() {
+            "jpg" | "jpeg" => Ok(ImageFormatExt::Jpeg),
+            "png" => Ok(ImageFormatExt::Png),
+            "webp" => Ok(ImageFormatExt::WebP),
+            "bmp" => Ok(ImageFormatExt::Bmp),
+            "tif" | "tiff" => Ok(ImageFormatExt::Tiff),
+            "gif" => Ok(ImageFormatExt::Gif),
+            "tga" => Ok(ImageFormatExt::Tga),
+            "ico" => Ok(ImageFormatExt::Ico),
+            "hdr" => Ok(ImageFormatExt::Hdr),
+            "exr" => Ok(ImageFormatExt::OpenExr),
+            "ff" | "farbfeld" => Ok(ImageFormatExt::Farbfeld),
+            "svg" => Ok(ImageFormatExt::Svg),
+            other => Err(format!("Unsupported image extension: '{}'", other)),
+        }
+    }
+}
+
+/// 指定した拡張子を持つファイルが入力として扱えるか
+pub fn is_supported_input(ext: &str) -> bool {
+    ImageFormatExt::from_str(ext).is_ok()
+}
+
+/// 出力先として互換性のある拡張子の一覧 (SVGを除く、エンコード可能な全形式)
+pub fn compatible_targets() -> Vec<&'static str> {
+    ImageFormatExt::ALL
+        .iter()
+        .filter(|f| f.supports_encode())
+        .map(|f| f.extension())
+        .collect()
+}
+
+/// 画像を読み込み、指定フォーマットへ変換して保存する
+pub fn convert_image(input_path: &Path, output_path: &Path, target: ImageFormatExt) -> Result<()> {
+    if !target.supports_encode() {
+        bail!(
+            "'{}' cannot be used as an output format (decode-only)",
+            target.extension()
+        );
+    }
+
+    let img = load_image(input_path)?;
+
+    let format = target
+        .image_format()
+        .expect("supports_encode() already checked that this format has an encoder");
+
+    img.save_with_format(output_path, format).with_context(|| {
+        format!(
+            "Failed to encode image as {}: {}",
+            target.extension(),
+            output_path.display()
+        )
+    })
+}
+
+fn load_image(input_path: &Path) -> Result<DynamicImage> {
+    let ext = input_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if ext == "svg" {
+        rasterize_svg(input_path)
+    } else {
+        image::open(input_path)
+            .with_context(|| format!("Failed to open image: {}", input_path.display()))
+    }
+}
+
+/// SVGを外部ラスタライザ (resvg/usvg) でビットマップ化する
+fn rasterize_svg(input_path: &Path) -> Result<DynamicImage> {
+    let svg_data = fs::read(input_path)
+        .with_context(|| format!("Failed to read SVG: {}", input_path.display()))?;
+
+    let opt = usvg::Options::default();
+    let tree = usvg::Tree::from_data(&svg_data, &opt)
+        .with_context(|| format!("Failed to parse SVG: {}", input_path.display()))?;
+
+    let size = tree.size.to_screen_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .context("Failed to allocate rasterization buffer")?;
+
+    resvg::render(
+        &tree,
+        usvg::FitTo::Original,
+        tiny_skia::Transform::default(),
+        pixmap.as_mut(),
+    )
+    .with_context(|| format!("Failed to rasterize SVG: {}", input_path.display()))?;
+
+    let rgba = RgbaImage::from_raw(size.width(), size.height(), pixmap.data().to_vec())
+        .context("Failed to build image buffer from rasterized SVG")?;
+
+    Ok(DynamicImage::ImageRgba8(rgba))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_accepts_known_extensions_case_insensitively() {
+        assert_eq!(ImageFormatExt::from_str("JPG").unwrap(), ImageFormatExt::Jpeg);
+        assert_eq!(ImageFormatExt::from_str("jpeg").unwrap(), ImageFormatExt::Jpeg);
+        assert_eq!(ImageFormatExt::from_str("Png").unwrap(), ImageFormatExt::Png);
+        assert_eq!(ImageFormatExt::from_str("tif").unwrap(), ImageFormatExt::Tiff);
+        assert_eq!(ImageFormatExt::from_str("svg").unwrap(), ImageFormatExt::Svg);
+    }
+
+    #[test]
+    fn from_str_rejects_unknown_extension() {
+        assert!(ImageFormatExt::from_str("psd").is_err());
+    }
+
+    #[test]
+    fn svg_supports_decode_only() {
+        assert!(!ImageFormatExt::Svg.supports_encode());
+        assert!(ImageFormatExt::Jpeg.supports_encode());
+    }
+
+    #[test]
+    fn compatible_targets_excludes_decode_only_formats() {
+        let targets = compatible_targets();
+        assert!(!targets.contains(&"svg"));
+        assert!(targets.contains(&"jpg"));
+        assert!(targets.contains(&"webp"));
+    }
+
+    #[test]
+    fn is_supported_input_accepts_svg_but_rejects_garbage() {
+        assert!(is_supported_input("svg"));
+        assert!(is_supported_input("png"));
+        assert!(!is_supported_input("psd"));
+    }
+}
@@ -0,0 +1,384 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::io::Reader as ImageReader;
+use image::{DynamicImage, ImageFormat};
+
+/// `--format` で指定する出力形式
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum FormatArg {
+    Jpeg,
+    Png,
+    #[value(name = "webp")]
+    WebP,
+    Avif,
+    /// 入力の拡張子から可逆/非可逆を推定して選択する
+    Auto,
+}
+
+/// 実際に使用する出力形式とそのパラメータ
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    Jpeg(u8),
+    Png,
+    WebP { quality: u8 },
+    Avif { quality: u8, speed: u8 },
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Jpeg(_) => "jpg",
+            Format::Png => "png",
+            Format::WebP { .. } => "webp",
+            Format::Avif { .. } => "avif",
+        }
+    }
+
+    /// この形式の非可逆エンコーダ設定 (品質の下限とエンコード関数)。PNGはNone (可逆、一度だけ保存)
+    fn lossy_encoder(&self) -> Option<LossyEncoder> {
+        match *self {
+            Format::Png => None,
+            Format::Jpeg(quality) => Some(LossyEncoder {
+                initial_quality: quality,
+                min_quality: 60,
+                encode: Box::new(save_jpeg),
+            }),
+            Format::WebP { quality } => Some(LossyEncoder {
+                initial_quality: quality,
+                min_quality: 60,
+                encode: Box::new(save_webp),
+            }),
+            Format::Avif { quality, speed } => Some(LossyEncoder {
+                initial_quality: quality,
+                // AVIFはJPEG/WebPよりずっと低い品質値でも見た目を保てる
+                min_quality: 20,
+                encode: Box::new(move |img, path, q| save_avif(img, path, q, speed)),
+            }),
+        }
+    }
+}
+
+/// `--format` の指定と入力画像の拡張子から実際の出力形式を決定する
+pub fn resolve_format(arg: FormatArg, quality: u8, avif_speed: u8, input_path: &Path) -> Format {
+    match arg {
+        FormatArg::Jpeg => Format::Jpeg(quality),
+        FormatArg::Png => Format::Png,
+        FormatArg::WebP => Format::WebP { quality },
+        FormatArg::Avif => Format::Avif {
+            quality,
+            speed: avif_speed,
+        },
+        FormatArg::Auto => {
+            let ext = input_path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+
+            if matches!(ext.as_str(), "jpg" | "jpeg" | "webp") {
+                // 非可逆な入力 → 再圧縮してもJPEGで問題ない
+                Format::Jpeg(quality)
+            } else {
+                // 可逆な入力 (png等) → 劣化のないPNGで出力
+                Format::Png
+            }
+        }
+    }
+}
+
+/// 画像の寸法と形式を、可能な限りフルデコードせずに読み取る
+pub fn read_image_metadata(path: &Path) -> Result<(u32, u32, ImageFormat)> {
+    let reader = ImageReader::open(path)
+        .with_context(|| format!("Failed to open image: {}", path.display()))?
+        .with_guessed_format()
+        .with_context(|| format!("Failed to detect image format: {}", path.display()))?;
+
+    let detected_format = reader
+        .format()
+        .with_context(|| format!("Could not determine image format: {}", path.display()))?;
+
+    let (width, height) = reader
+        .into_dimensions()
+        .with_context(|| format!("Failed to read image dimensions: {}", path.display()))?;
+
+    Ok((width, height, detected_format))
+}
+
+/// 非可逆エンコーダのエンコード関数シグネチャ (画像, 出力先, 品質) -> 結果
+type EncodeFn = dyn Fn(&DynamicImage, &Path, u8) -> Result<()>;
+
+/// 非可逆形式1つ分のエンコード設定。形式ごとに品質の下限とエンコード処理が異なる
+struct LossyEncoder {
+    initial_quality: u8,
+    min_quality: u8,
+    encode: Box<EncodeFn>,
+}
+
+/// サイズ制限付きで画像を保存する
+///
+/// 非可逆形式 (JPEG/WebP/AVIF) は品質を調整しながら再エンコードし、
+/// 可逆形式 (PNG) は一度だけエンコードする。
+/// 戻り値は (最終ファイルサイズ, 調整後の品質 [非可逆のみ])
+pub fn save_with_size_limit(
+    img: &DynamicImage,
+    output_path: &Path,
+    format: Format,
+    max_size_bytes: usize,
+) -> Result<(usize, Option<u8>)> {
+    match format.lossy_encoder() {
+        None => {
+            save_png(img, output_path)?;
+            let size = fs::metadata(output_path)
+                .with_context(|| format!("Failed to get metadata: {}", output_path.display()))?
+                .len() as usize;
+            Ok((size, None))
+        }
+        Some(encoder) => {
+            let (size, quality) = save_lossy_with_size_limit(img, output_path, &encoder, max_size_bytes)?;
+            Ok((size, Some(quality)))
+        }
+    }
+}
+
+/// 一時ファイルに指定品質でエンコードし、そのファイルサイズを返す
+fn encode_and_measure(
+    img: &DynamicImage,
+    temp_path: &Path,
+    quality: u8,
+    encode: &EncodeFn,
+) -> Result<usize> {
+    encode(img, temp_path, quality)?;
+    let size = fs::metadata(temp_path)
+        .with_context(|| format!("Failed to get metadata: {}", temp_path.display()))?
+        .len() as usize;
+    Ok(size)
+}
+
+/// `[min_quality, initial_quality]` を二分探索し、サイズ上限に収まる最高品質を探す
+/// (探索ループ自体はフォーマットに依存しない)
+fn save_lossy_with_size_limit(
+    img: &DynamicImage,
+    output_path: &Path,
+    encoder: &LossyEncoder,
+    max_size_bytes: usize,
+) -> Result<(usize, u8)> {
+    const QUALITY_STEP: u8 = 5;
+
+    let LossyEncoder {
+        initial_quality,
+        min_quality,
+        encode,
+    } = encoder;
+    let (initial_quality, min_quality) = (*initial_quality, *min_quality);
+
+    let temp_path = output_path.with_extension("tmp");
+
+    if initial_quality <= min_quality {
+        // 探索の余地がないのでそのまま保存
+        let size = encode_and_measure(img, &temp_path, initial_quality, encode)?;
+        fs::rename(&temp_path, output_path)
+            .with_context(|| format!("Failed to rename file: {}", output_path.display()))?;
+        return Ok((size, initial_quality));
+    }
+
+    // 上限 (初期品質) がすでに収まるなら探索不要
+    let high_size = encode_and_measure(img, &temp_path, initial_quality, encode)?;
+    if high_size <= max_size_bytes {
+        fs::rename(&temp_path, output_path)
+            .with_context(|| format!("Failed to rename file: {}", output_path.display()))?;
+        return Ok((high_size, initial_quality));
+    }
+
+    // 下限 (min_quality) でも収まらないなら、それをそのまま採用する (既存の不変条件)
+    let low_size = encode_and_measure(img, &temp_path, min_quality, encode)?;
+    if low_size > max_size_bytes {
+        fs::rename(&temp_path, output_path)
+            .with_context(|| format!("Failed to rename file: {}", output_path.display()))?;
+        return Ok((low_size, min_quality));
+    }
+
+    // min_quality のエンコードは収まることを確認済みなので、これを現時点のベストとして保持しておく
+    // (探索の過程でより良い候補が見つかるたびに上書きし、最後に使い回すことで再エンコードを避ける)
+    let best_path = output_path.with_extension("tmp.best");
+    fs::rename(&temp_path, &best_path)
+        .with_context(|| format!("Failed to rename file: {}", best_path.display()))?;
+    let mut best_size = low_size;
+    let mut best_quality = min_quality;
+
+    // 収まる範囲で最も高い品質を二分探索する
+    let mut low = min_quality;
+    let mut high = initial_quality;
+
+    while high - low > QUALITY_STEP {
+        let mid = low + (high - low) / 2;
+        let size = encode_and_measure(img, &temp_path, mid, encode)?;
+
+        if size <= max_size_bytes {
+            low = mid;
+            fs::rename(&temp_path, &best_path)
+                .with_context(|| format!("Failed to rename file: {}", best_path.display()))?;
+            best_size = size;
+            best_quality = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    // 探索中に見つかった最良のエンコード結果をそのまま確定保存する (再エンコードしない)
+    fs::rename(&best_path, output_path)
+        .with_context(|| format!("Failed to rename file: {}", output_path.display()))?;
+    Ok((best_size, best_quality))
+}
+
+/// JPEG形式で画像を保存
+fn save_jpeg(img: &DynamicImage, path: &Path, quality: u8) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create file: {}", path.display()))?;
+
+    let mut writer = BufWriter::new(file);
+
+    let rgb_img = img.to_rgb8();
+    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut writer, quality);
+
+    encoder
+        .encode(
+            rgb_img.as_raw(),
+            rgb_img.width(),
+            rgb_img.height(),
+            image::ColorType::Rgb8,
+        )
+        .with_context(|| format!("Failed to encode JPEG: {}", path.display()))?;
+
+    Ok(())
+}
+
+/// WebP形式で画像を保存 (品質指定の非可逆圧縮)
+fn save_webp(img: &DynamicImage, path: &Path, quality: u8) -> Result<()> {
+    let rgba_img = img.to_rgba8();
+    let encoder = webp::Encoder::from_rgba(&rgba_img, rgba_img.width(), rgba_img.height());
+    let encoded = encoder.encode(quality as f32);
+
+    fs::write(path, &*encoded)
+        .with_context(|| format!("Failed to write WebP file: {}", path.display()))?;
+
+    Ok(())
+}
+
+/// AVIF形式で画像を保存 (品質・エンコード速度指定の非可逆圧縮)
+///
+/// speedは1 (最も遅い・最も小さい) 〜 10 (最も速い・やや大きい) の範囲
+/// (`ravif::Encoder::with_speed` は1..=10の範囲外だとpanicするため、呼び出し側で検証済みであること)
+fn save_avif(img: &DynamicImage, path: &Path, quality: u8, speed: u8) -> Result<()> {
+    let rgba_img = img.to_rgba8();
+    let (width, height) = rgba_img.dimensions();
+
+    let pixels: Vec<ravif::RGBA8> = rgba_img
+        .pixels()
+        .map(|p| ravif::RGBA8::new(p[0], p[1], p[2], p[3]))
+        .collect();
+    let source = ravif::Img::new(pixels.as_slice(), width as usize, height as usize);
+
+    let encoded = ravif::Encoder::new()
+        .with_quality(quality as f32)
+        .with_speed(speed)
+        .encode_rgba(source)
+        .with_context(|| format!("Failed to encode AVIF: {}", path.display()))?;
+
+    fs::write(path, encoded.avif_file)
+        .with_context(|| format!("Failed to write AVIF file: {}", path.display()))?;
+
+    Ok(())
+}
+
+/// PNG形式で画像を保存 (可逆圧縮、品質指定なし)
+fn save_png(img: &DynamicImage, path: &Path) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create file: {}", path.display()))?;
+
+    let mut writer = BufWriter::new(file);
+    let encoder = image::codecs::png::PngEncoder::new(&mut writer);
+
+    img.write_with_encoder(encoder)
+        .with_context(|| format!("Failed to encode PNG: {}", path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// qualityに比例したサイズのダミーファイルを書き出す、テスト専用のエンコーダ
+    fn fake_encode(_img: &DynamicImage, path: &Path, quality: u8) -> Result<()> {
+        fs::write(path, vec![0u8; quality as usize * 1000])?;
+        Ok(())
+    }
+
+    fn encoder(initial_quality: u8, min_quality: u8) -> LossyEncoder {
+        LossyEncoder {
+            initial_quality,
+            min_quality,
+            encode: Box::new(fake_encode),
+        }
+    }
+
+    fn tiny_image() -> DynamicImage {
+        DynamicImage::new_rgba8(1, 1)
+    }
+
+    #[test]
+    fn no_search_room_encodes_once_at_initial_quality() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.jpg");
+
+        let (size, quality) =
+            save_lossy_with_size_limit(&tiny_image(), &output, &encoder(60, 60), 1_000_000).unwrap();
+
+        assert_eq!(quality, 60);
+        assert_eq!(size, 60_000);
+        assert!(output.exists());
+    }
+
+    #[test]
+    fn initial_quality_already_fits_skips_the_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.jpg");
+
+        let (size, quality) =
+            save_lossy_with_size_limit(&tiny_image(), &output, &encoder(90, 10), 1_000_000).unwrap();
+
+        assert_eq!(quality, 90);
+        assert_eq!(size, 90_000);
+    }
+
+    #[test]
+    fn even_min_quality_over_limit_still_emits_min_quality() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.jpg");
+
+        let (size, quality) =
+            save_lossy_with_size_limit(&tiny_image(), &output, &encoder(90, 10), 5_000).unwrap();
+
+        assert_eq!(quality, 10);
+        assert_eq!(size, 10_000);
+        assert!(output.exists());
+    }
+
+    #[test]
+    fn binary_search_finds_highest_quality_within_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.jpg");
+
+        // quality*1000バイトとして、50000バイト以下に収まる最大のqualityは50
+        let (size, quality) =
+            save_lossy_with_size_limit(&tiny_image(), &output, &encoder(90, 10), 50_000).unwrap();
+
+        assert!(size <= 50_000);
+        assert!(quality <= 50);
+        // 二分探索の打ち切り幅 (QUALITY_STEP=5) より近い解を見逃していないこと
+        assert!(quality >= 45);
+    }
+}
@@ -1,15 +1,26 @@
+mod cache;
+mod convert;
+mod format;
+mod report;
+mod resize;
+
 use anyhow::{Context, Result};
 use clap::Parser;
-use image::{DynamicImage, GenericImageView, RgbaImage};
+use image::GenericImageView;
 use rayon::prelude::*;
-use std::fs::{self, File};
-use std::io::BufWriter;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::Instant;
 use walkdir::WalkDir;
 
-/// 画像バッチ処理ツール - 4:5のアスペクト比に変換し、8MB以下に圧縮
+use format::FormatArg;
+use report::ReportEntry;
+use resize::{AspectRatio, ResizeOp};
+
+/// 画像バッチ処理ツール - アスペクト比変換・リサイズを行い、指定サイズ以下に圧縮
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -21,33 +32,59 @@ struct Args {
     #[arg(short, long, default_value = "crop")]
     mode: ConversionMode,
 
+    /// 目標アスペクト比 (例: 4:5, 1:1, 16:9)
+    #[arg(long, default_value = "4:5")]
+    aspect_ratio: AspectRatio,
+
     /// パディング時の背景色 (white または black)
     #[arg(short, long, default_value = "white")]
     bg_color: BackgroundColor,
 
-    /// 初期JPEG品質 (1-100)
+    /// アスペクト比変換の後に適用する追加のリサイズ操作
+    /// (例: fit:2048x2048, fit-width:1024, scale:800x600)
+    #[arg(long)]
+    resize: Option<ResizeOp>,
+
+    /// 出力フォーマット (jpeg, png, webp, avif, auto)
+    #[arg(long, default_value = "auto", value_enum)]
+    format: FormatArg,
+
+    /// AVIFのエンコード速度 (1=最も遅い・最も高圧縮 〜 10=最も速い)
+    #[arg(long, default_value = "6")]
+    avif_speed: u8,
+
+    /// 初期品質 (1-100、非可逆フォーマットのみ)
     #[arg(short, long, default_value = "90")]
     quality: u8,
 
     /// 最大ファイルサイズ (MB)
     #[arg(long, default_value = "8")]
     max_size: usize,
+
+    /// バッチ結果をJSON/CSVとして書き出すパス (拡張子で判定)
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// アスペクト比変換を行わず、指定フォーマットへの単純な変換のみ行う
+    /// (例: --convert-to webp)
+    #[arg(long)]
+    convert_to: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
-enum ConversionMode {
+pub(crate) enum ConversionMode {
     Crop,
     Pad,
 }
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
-enum BackgroundColor {
+pub(crate) enum BackgroundColor {
     White,
     Black,
 }
 
 impl BackgroundColor {
-    fn to_rgba(&self) -> image::Rgba<u8> {
+    pub(crate) fn to_rgba(self) -> image::Rgba<u8> {
         match self {
             BackgroundColor::White => image::Rgba([255, 255, 255, 255]),
             BackgroundColor::Black => image::Rgba([0, 0, 0, 255]),
@@ -59,8 +96,38 @@ impl BackgroundColor {
 struct ProcessResult {
     input_path: PathBuf,
     output_path: PathBuf,
-    final_size_mb: f64,
+    original_width: u32,
+    original_height: u32,
+    final_width: u32,
+    final_height: u32,
+    original_size_bytes: u64,
+    final_size_bytes: u64,
+    format: String,
     final_quality: Option<u8>,
+    /// キャッシュされた出力を再利用した場合 true
+    cached: bool,
+}
+
+impl ProcessResult {
+    fn final_size_mb(&self) -> f64 {
+        self.final_size_bytes as f64 / (1024.0 * 1024.0)
+    }
+
+    fn to_report_entry(&self, status: &str) -> ReportEntry {
+        ReportEntry {
+            input_path: self.input_path.clone(),
+            output_path: Some(self.output_path.clone()),
+            original_width: Some(self.original_width),
+            original_height: Some(self.original_height),
+            final_width: Some(self.final_width),
+            final_height: Some(self.final_height),
+            original_size_bytes: Some(self.original_size_bytes),
+            final_size_bytes: Some(self.final_size_bytes),
+            format: Some(self.format.clone()),
+            final_quality: self.final_quality,
+            status: status.to_string(),
+        }
+    }
 }
 
 fn main() -> Result<()> {
@@ -71,6 +138,11 @@ fn main() -> Result<()> {
         anyhow::bail!("Quality must be between 1 and 100");
     }
 
+    // AVIFエンコード速度の検証 (ravif::Encoder::with_speedは1..=10のみ受け付け、範囲外はpanicする)
+    if args.avif_speed == 0 || args.avif_speed > 10 {
+        anyhow::bail!("AVIF speed must be between 1 and 10");
+    }
+
     // 入力フォルダーの検証
     if !args.input.exists() {
         anyhow::bail!("Input folder does not exist: {}", args.input.display());
@@ -81,8 +153,30 @@ fn main() -> Result<()> {
 
     println!("Processing images in: {}", args.input.display());
 
+    // --convert-to が指定された場合は、アスペクト比変換を行わず単純に変換する
+    if let Some(target_ext) = &args.convert_to {
+        let image_files = collect_image_files(&args.input, |path| {
+            if cache::is_cache_output(path) {
+                return false;
+            }
+            path.extension()
+                .and_then(|e| e.to_str())
+                .map(convert::is_supported_input)
+                .unwrap_or(false)
+        })?;
+
+        if image_files.is_empty() {
+            println!("No image files found.");
+            return Ok(());
+        }
+
+        println!("Found {} images\n", image_files.len());
+
+        return run_convert_mode(&image_files, target_ext, args.report.as_deref());
+    }
+
     // 画像ファイルを収集
-    let image_files = collect_image_files(&args.input)?;
+    let image_files = collect_image_files(&args.input, is_supported_image)?;
     let total_count = image_files.len();
 
     if total_count == 0 {
@@ -96,11 +190,12 @@ fn main() -> Result<()> {
     let success_count = AtomicUsize::new(0);
     let failed_count = AtomicUsize::new(0);
     let processed_count = AtomicUsize::new(0);
+    let report_entries: Mutex<Vec<ReportEntry>> = Mutex::new(Vec::with_capacity(total_count));
 
-    // 並列処理で画像を処理
-    let _results: Vec<_> = image_files
+    // 並列処理で画像を処理 (集計結果はreport_entries/各カウンタ経由で受け取るため戻り値は不要)
+    image_files
         .par_iter()
-        .filter_map(|path| {
+        .for_each(|path| {
             let current = processed_count.fetch_add(1, Ordering::SeqCst) + 1;
 
             match process_image(path, &args) {
@@ -112,18 +207,23 @@ fn main() -> Result<()> {
                     } else {
                         String::new()
                     };
+                    let cached_info = if result.cached { ", cached" } else { "" };
 
                     println!(
-                        "[{}/{}] {} → {} ({:.1} MB{}) ✓",
+                        "[{}/{}] {} → {} ({:.1} MB{}{}) ✓",
                         current,
                         total_count,
                         path.file_name().unwrap().to_string_lossy(),
                         result.output_path.file_name().unwrap().to_string_lossy(),
-                        result.final_size_mb,
-                        quality_info
+                        result.final_size_mb(),
+                        quality_info,
+                        cached_info
                     );
 
-                    Some(result)
+                    if args.report.is_some() {
+                        let entry = result.to_report_entry("success");
+                        report_entries.lock().unwrap().push(entry);
+                    }
                 }
                 Err(e) => {
                     failed_count.fetch_add(1, Ordering::SeqCst);
@@ -134,11 +234,29 @@ fn main() -> Result<()> {
                         path.file_name().unwrap().to_string_lossy(),
                         e
                     );
-                    None
+
+                    if args.report.is_some() {
+                        let (original_width, original_height) = format::read_image_metadata(path)
+                            .map(|(w, h, _)| (Some(w), Some(h)))
+                            .unwrap_or((None, None));
+
+                        report_entries.lock().unwrap().push(ReportEntry {
+                            input_path: path.clone(),
+                            output_path: None,
+                            original_width,
+                            original_height,
+                            final_width: None,
+                            final_height: None,
+                            original_size_bytes: fs::metadata(path).ok().map(|m| m.len()),
+                            final_size_bytes: None,
+                            format: None,
+                            final_quality: None,
+                            status: format!("error: {}", e),
+                        });
+                    }
                 }
             }
-        })
-        .collect();
+        });
 
     let duration = start.elapsed();
     let success = success_count.load(Ordering::SeqCst);
@@ -150,11 +268,17 @@ fn main() -> Result<()> {
     );
     println!("Total time: {:.1}s", duration.as_secs_f64());
 
+    if let Some(report_path) = &args.report {
+        let entries = report_entries.into_inner().unwrap();
+        report::write_report(report_path, &entries)?;
+        println!("Report written to: {}", report_path.display());
+    }
+
     Ok(())
 }
 
-/// 指定フォルダー内の画像ファイルを収集
-fn collect_image_files(dir: &Path) -> Result<Vec<PathBuf>> {
+/// 指定フォルダー内の画像ファイルを収集する (predicateに一致するファイルのみ)
+fn collect_image_files(dir: &Path, predicate: impl Fn(&Path) -> bool) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
     for entry in WalkDir::new(dir)
@@ -163,7 +287,7 @@ fn collect_image_files(dir: &Path) -> Result<Vec<PathBuf>> {
         .filter_map(|e| e.ok())
     {
         let path = entry.path();
-        if path.is_file() && is_supported_image(path) {
+        if path.is_file() && predicate(path) {
             files.push(path.to_path_buf());
         }
     }
@@ -171,179 +295,272 @@ fn collect_image_files(dir: &Path) -> Result<Vec<PathBuf>> {
     Ok(files)
 }
 
-/// サポートされている画像形式かチェック
-fn is_supported_image(path: &Path) -> bool {
-    if let Some(ext) = path.extension() {
-        let ext = ext.to_string_lossy().to_lowercase();
-        matches!(ext.as_str(), "jpg" | "jpeg" | "png" | "webp")
-    } else {
-        false
+/// `--convert-to` モード: アスペクト比変換を行わず、指定フォーマットへ変換するだけの処理
+fn run_convert_mode(
+    image_files: &[PathBuf],
+    target_ext: &str,
+    report_path: Option<&Path>,
+) -> Result<()> {
+    let target = convert::ImageFormatExt::from_str(target_ext).map_err(anyhow::Error::msg)?;
+
+    if !target.supports_encode() {
+        anyhow::bail!(
+            "'{}' cannot be used as an output format (decode-only). Supported targets: {}",
+            target.extension(),
+            convert::compatible_targets().join(", ")
+        );
     }
-}
-
-/// 画像を処理
-fn process_image(input_path: &Path, args: &Args) -> Result<ProcessResult> {
-    // 画像を読み込む
-    let img = image::open(input_path)
-        .with_context(|| format!("Failed to open image: {}", input_path.display()))?;
 
-    // 4:5のアスペクト比に変換
-    let converted = match args.mode {
-        ConversionMode::Crop => convert_aspect_ratio_crop(img),
-        ConversionMode::Pad => convert_aspect_ratio_pad(img, args.bg_color),
-    };
-
-    // 出力パスを生成
-    let output_path = generate_output_path(input_path)?;
+    let total_count = image_files.len();
+    let start = Instant::now();
+    let success_count = AtomicUsize::new(0);
+    let failed_count = AtomicUsize::new(0);
+    let skipped_count = AtomicUsize::new(0);
+    let processed_count = AtomicUsize::new(0);
+    let report_entries: Mutex<Vec<ReportEntry>> = Mutex::new(Vec::with_capacity(total_count));
+
+    image_files.par_iter().for_each(|path| {
+        let current = processed_count.fetch_add(1, Ordering::SeqCst) + 1;
+        let output_path = path.with_extension(target.extension());
+
+        let (original_width, original_height) = format::read_image_metadata(path)
+            .map(|(w, h, _)| (Some(w), Some(h)))
+            .unwrap_or((None, None));
+        let original_size_bytes = fs::metadata(path).ok().map(|m| m.len());
+
+        // 入力の拡張子がすでに変換先と一致する場合、出力先が入力と同じパスになり
+        // 元画像を上書きしてしまうため処理をスキップする
+        let input_ext_matches_target = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case(target.extension()))
+            .unwrap_or(false);
+
+        if input_ext_matches_target {
+            skipped_count.fetch_add(1, Ordering::SeqCst);
+            println!(
+                "[{}/{}] {} は既に {} 形式のためスキップします",
+                current,
+                total_count,
+                path.file_name().unwrap().to_string_lossy(),
+                target.extension()
+            );
+
+            if report_path.is_some() {
+                report_entries.lock().unwrap().push(ReportEntry {
+                    input_path: path.clone(),
+                    output_path: None,
+                    original_width,
+                    original_height,
+                    final_width: None,
+                    final_height: None,
+                    original_size_bytes,
+                    final_size_bytes: None,
+                    format: None,
+                    final_quality: None,
+                    status: "skipped: already in target format".to_string(),
+                });
+            }
+            return;
+        }
 
-    // 最大ファイルサイズ (バイト)
-    let max_size_bytes = args.max_size * 1024 * 1024;
+        match convert::convert_image(path, &output_path, target) {
+            Ok(()) => {
+                success_count.fetch_add(1, Ordering::SeqCst);
+                println!(
+                    "[{}/{}] {} → {} ✓",
+                    current,
+                    total_count,
+                    path.file_name().unwrap().to_string_lossy(),
+                    output_path.file_name().unwrap().to_string_lossy()
+                );
+
+                if report_path.is_some() {
+                    let (final_width, final_height) = format::read_image_metadata(&output_path)
+                        .map(|(w, h, _)| (Some(w), Some(h)))
+                        .unwrap_or((None, None));
+                    let final_size_bytes = fs::metadata(&output_path).ok().map(|m| m.len());
+
+                    report_entries.lock().unwrap().push(ReportEntry {
+                        input_path: path.clone(),
+                        output_path: Some(output_path.clone()),
+                        original_width,
+                        original_height,
+                        final_width,
+                        final_height,
+                        original_size_bytes,
+                        final_size_bytes,
+                        format: Some(target.extension().to_string()),
+                        final_quality: None,
+                        status: "success".to_string(),
+                    });
+                }
+            }
+            Err(e) => {
+                failed_count.fetch_add(1, Ordering::SeqCst);
+                eprintln!(
+                    "[{}/{}] {} ✗ Error: {}",
+                    current,
+                    total_count,
+                    path.file_name().unwrap().to_string_lossy(),
+                    e
+                );
+
+                if report_path.is_some() {
+                    report_entries.lock().unwrap().push(ReportEntry {
+                        input_path: path.clone(),
+                        output_path: None,
+                        original_width,
+                        original_height,
+                        final_width: None,
+                        final_height: None,
+                        original_size_bytes,
+                        final_size_bytes: None,
+                        format: None,
+                        final_quality: None,
+                        status: format!("error: {}", e),
+                    });
+                }
+            }
+        }
+    });
 
-    // 品質を調整しながら保存
-    let (final_size, final_quality) =
-        save_with_size_limit(&converted, &output_path, args.quality, max_size_bytes)?;
+    let duration = start.elapsed();
+    println!(
+        "\nCompleted: {} successful, {} failed, {} skipped (already in target format)",
+        success_count.load(Ordering::SeqCst),
+        failed_count.load(Ordering::SeqCst),
+        skipped_count.load(Ordering::SeqCst)
+    );
+    println!("Total time: {:.1}s", duration.as_secs_f64());
 
-    let final_size_mb = final_size as f64 / (1024.0 * 1024.0);
+    if let Some(report_path) = report_path {
+        let entries = report_entries.into_inner().unwrap();
+        report::write_report(report_path, &entries)?;
+        println!("Report written to: {}", report_path.display());
+    }
 
-    Ok(ProcessResult {
-        input_path: input_path.to_path_buf(),
-        output_path,
-        final_size_mb,
-        final_quality: if final_quality < args.quality {
-            Some(final_quality)
-        } else {
-            None
-        },
-    })
+    Ok(())
 }
 
-/// 4:5のアスペクト比に変換 (中央クロップ)
-fn convert_aspect_ratio_crop(img: DynamicImage) -> DynamicImage {
-    let (width, height) = img.dimensions();
-    let target_ratio = 4.0 / 5.0; // 0.8
-    let current_ratio = width as f64 / height as f64;
-
-    if (current_ratio - target_ratio).abs() < 0.001 {
-        // 既に4:5の場合はそのまま
-        return img;
+/// サポートされている画像形式かチェック
+///
+/// このツール自身が書き出したキャッシュ出力 (`<stem>.<ext>.<hash><opt>.<ext>`) は
+/// 拡張子だけ見ると通常の画像と区別できないため、次回実行時に新規入力として
+/// 再度拾い上げて世代を重ねて再エンコードしてしまわないよう明示的に除外する
+fn is_supported_image(path: &Path) -> bool {
+    if cache::is_cache_output(path) {
+        return false;
     }
 
-    let (crop_width, crop_height) = if current_ratio > target_ratio {
-        // 横長すぎる → 幅を削る
-        let new_width = (height as f64 * target_ratio).round() as u32;
-        (new_width, height)
+    if let Some(ext) = path.extension() {
+        let ext = ext.to_string_lossy().to_lowercase();
+        matches!(ext.as_str(), "jpg" | "jpeg" | "png" | "webp")
     } else {
-        // 縦長すぎる → 高さを削る
-        let new_height = (width as f64 / target_ratio).round() as u32;
-        (width, new_height)
-    };
-
-    let x = (width.saturating_sub(crop_width)) / 2;
-    let y = (height.saturating_sub(crop_height)) / 2;
-
-    img.crop_imm(x, y, crop_width, crop_height)
-}
-
-/// 4:5のアスペクト比に変換 (パディング)
-fn convert_aspect_ratio_pad(img: DynamicImage, bg_color: BackgroundColor) -> DynamicImage {
-    let (width, height) = img.dimensions();
-    let target_ratio = 4.0 / 5.0; // 0.8
-    let current_ratio = width as f64 / height as f64;
-
-    if (current_ratio - target_ratio).abs() < 0.001 {
-        // 既に4:5の場合はそのまま
-        return img;
+        false
     }
+}
 
-    let (new_width, new_height) = if current_ratio > target_ratio {
-        // 横長すぎる → 上下にパディング
-        let new_height = (width as f64 / target_ratio).round() as u32;
-        (width, new_height)
-    } else {
-        // 縦長すぎる → 左右にパディング
-        let new_width = (height as f64 * target_ratio).round() as u32;
-        (new_width, height)
+/// 画像を処理
+fn process_image(input_path: &Path, args: &Args) -> Result<ProcessResult> {
+    let input_bytes = fs::read(input_path)
+        .with_context(|| format!("Failed to read image: {}", input_path.display()))?;
+    let original_size_bytes = input_bytes.len() as u64;
+    let (original_width, original_height, _) = format::read_image_metadata(input_path)?;
+
+    // 出力フォーマットを決定し、設定からキャッシュキーを算出
+    let out_format = format::resolve_format(args.format, args.quality, args.avif_speed, input_path);
+    let settings = cache::Settings {
+        mode: args.mode,
+        aspect_ratio: args.aspect_ratio,
+        bg_color: args.bg_color,
+        resize: args.resize,
+        quality: args.quality,
+        max_size: args.max_size,
+        format: out_format,
     };
+    let key = cache::compute_key(&input_bytes, &settings);
 
-    let mut canvas = RgbaImage::from_pixel(new_width, new_height, bg_color.to_rgba());
-
-    let x = (new_width.saturating_sub(width)) / 2;
-    let y = (new_height.saturating_sub(height)) / 2;
-
-    image::imageops::overlay(&mut canvas, &img.to_rgba8(), x.into(), y.into());
-
-    DynamicImage::ImageRgba8(canvas)
-}
-
-/// 出力パスを生成
-fn generate_output_path(input_path: &Path) -> Result<PathBuf> {
     let parent = input_path
         .parent()
         .context("Failed to get parent directory")?;
-
     let stem = input_path
         .file_stem()
         .context("Failed to get file stem")?
         .to_string_lossy();
+    let source_ext = input_path
+        .extension()
+        .context("Failed to get file extension")?
+        .to_string_lossy()
+        .to_lowercase();
+    let output_path =
+        cache::cached_output_path(parent, &stem, &source_ext, key, out_format.extension());
+
+    // 設定が変わって不要になった古いキャッシュ出力を掃除する (同じ入力ファイル由来のもののみ)
+    cache::sweep_stale_outputs(parent, &stem, &source_ext, &output_path)?;
+
+    if output_path.exists() {
+        let cached_size = fs::metadata(&output_path)
+            .with_context(|| format!("Failed to get metadata: {}", output_path.display()))?
+            .len();
+        let (final_width, final_height, _) = format::read_image_metadata(&output_path)?;
+
+        return Ok(ProcessResult {
+            input_path: input_path.to_path_buf(),
+            output_path,
+            original_width,
+            original_height,
+            final_width,
+            final_height,
+            original_size_bytes,
+            final_size_bytes: cached_size,
+            format: out_format.extension().to_string(),
+            final_quality: None,
+            cached: true,
+        });
+    }
 
-    let output_filename = format!("{}_processed.jpg", stem);
-
-    Ok(parent.join(output_filename))
-}
+    // 画像を読み込む
+    let img = image::load_from_memory(&input_bytes)
+        .with_context(|| format!("Failed to open image: {}", input_path.display()))?;
 
-/// サイズ制限付きで画像を保存
-fn save_with_size_limit(
-    img: &DynamicImage,
-    output_path: &Path,
-    initial_quality: u8,
-    max_size_bytes: usize,
-) -> Result<(usize, u8)> {
-    const MIN_QUALITY: u8 = 60;
-    const QUALITY_STEP: u8 = 5;
-
-    let mut quality = initial_quality;
-
-    loop {
-        // 一時ファイルに保存
-        let temp_path = output_path.with_extension("tmp.jpg");
-        save_jpeg(img, &temp_path, quality)?;
-
-        // ファイルサイズを確認
-        let metadata = fs::metadata(&temp_path)
-            .with_context(|| format!("Failed to get metadata: {}", temp_path.display()))?;
-        let file_size = metadata.len() as usize;
-
-        if file_size <= max_size_bytes || quality <= MIN_QUALITY {
-            // サイズが制限内、または最小品質に達した
-            fs::rename(&temp_path, output_path)
-                .with_context(|| format!("Failed to rename file: {}", output_path.display()))?;
-            return Ok((file_size, quality));
+    // 指定のアスペクト比に変換
+    let converted = match args.mode {
+        ConversionMode::Crop => resize::convert_aspect_ratio_crop(img, args.aspect_ratio),
+        ConversionMode::Pad => {
+            resize::convert_aspect_ratio_pad(img, args.aspect_ratio, args.bg_color)
         }
+    };
 
-        // 品質を下げて再試行
-        fs::remove_file(&temp_path).ok();
-        quality = quality.saturating_sub(QUALITY_STEP).max(MIN_QUALITY);
-    }
-}
-
-/// JPEG形式で画像を保存
-fn save_jpeg(img: &DynamicImage, path: &Path, quality: u8) -> Result<()> {
-    let file = File::create(path)
-        .with_context(|| format!("Failed to create file: {}", path.display()))?;
+    // 追加のリサイズ操作 (例: 出力サイズの上限)
+    let converted = match args.resize {
+        Some(op) => op.apply(converted),
+        None => converted,
+    };
 
-    let mut writer = BufWriter::new(file);
+    let (final_width, final_height) = converted.dimensions();
 
-    let rgb_img = img.to_rgb8();
-    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut writer, quality);
+    // 最大ファイルサイズ (バイト)
+    let max_size_bytes = args.max_size * 1024 * 1024;
 
-    encoder
-        .encode(
-            rgb_img.as_raw(),
-            rgb_img.width(),
-            rgb_img.height(),
-            image::ColorType::Rgb8,
-        )
-        .with_context(|| format!("Failed to encode JPEG: {}", path.display()))?;
+    // 品質を調整しながら保存 (可逆フォーマットは一度だけエンコード)
+    let (final_size, final_quality) =
+        format::save_with_size_limit(&converted, &output_path, out_format, max_size_bytes)?;
 
-    Ok(())
+    Ok(ProcessResult {
+        input_path: input_path.to_path_buf(),
+        output_path,
+        original_width,
+        original_height,
+        final_width,
+        final_height,
+        original_size_bytes,
+        final_size_bytes: final_size as u64,
+        format: out_format.extension().to_string(),
+        final_quality: match final_quality {
+            Some(q) if q < args.quality => Some(q),
+            _ => None,
+        },
+        cached: false,
+    })
 }
@@ -0,0 +1,67 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+/// 1ファイル分の処理結果 (`--report` 出力用)
+#[derive(Debug, Serialize)]
+pub struct ReportEntry {
+    pub input_path: PathBuf,
+    pub output_path: Option<PathBuf>,
+    pub original_width: Option<u32>,
+    pub original_height: Option<u32>,
+    pub final_width: Option<u32>,
+    pub final_height: Option<u32>,
+    pub original_size_bytes: Option<u64>,
+    pub final_size_bytes: Option<u64>,
+    pub format: Option<String>,
+    pub final_quality: Option<u8>,
+    pub status: String,
+}
+
+/// 収集した結果を拡張子 (`.json` / `.csv`) に応じて書き出す
+pub fn write_report(path: &Path, entries: &[ReportEntry]) -> Result<()> {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .as_deref()
+    {
+        Some("json") => write_json(path, entries),
+        Some("csv") => write_csv(path, entries),
+        _ => bail!(
+            "Unsupported report extension (expected .json or .csv): {}",
+            path.display()
+        ),
+    }
+}
+
+fn write_json(path: &Path, entries: &[ReportEntry]) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create report file: {}", path.display()))?;
+    let writer = BufWriter::new(file);
+
+    serde_json::to_writer_pretty(writer, entries)
+        .with_context(|| format!("Failed to write JSON report: {}", path.display()))?;
+
+    Ok(())
+}
+
+fn write_csv(path: &Path, entries: &[ReportEntry]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)
+        .with_context(|| format!("Failed to create report file: {}", path.display()))?;
+
+    for entry in entries {
+        writer
+            .serialize(entry)
+            .with_context(|| format!("Failed to write report row for: {}", entry.input_path.display()))?;
+    }
+
+    writer
+        .flush()
+        .with_context(|| format!("Failed to flush report file: {}", path.display()))?;
+
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use std::str::FromStr;
+
+use image::{imageops::FilterType, DynamicImage, GenericImageView, RgbaImage};
+
+use crate::BackgroundColor;
+
+/// 目標アスペクト比 (幅:高さ)
+#[derive(Debug, Clone, Copy)]
+pub struct AspectRatio {
+    pub w: u32,
+    pub h: u32,
+}
+
+impl AspectRatio {
+    pub fn ratio(&self) -> f64 {
+        self.w as f64 / self.h as f64
+    }
+}
+
+impl FromStr for AspectRatio {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (w, h) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid aspect ratio '{}', expected format W:H", s))?;
+
+        let w: u32 = w
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid aspect ratio width: '{}'", w))?;
+        let h: u32 = h
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid aspect ratio height: '{}'", h))?;
+
+        if w == 0 || h == 0 {
+            return Err("Aspect ratio components must be non-zero".to_string());
+        }
+
+        Ok(AspectRatio { w, h })
+    }
+}
+
+/// 汎用リサイズ操作
+///
+/// `--resize` で指定し、アスペクト比変換の後・サイズ上限調整の前に適用する。
+#[derive(Debug, Clone, Copy)]
+pub enum ResizeOp {
+    /// 指定したピクセルサイズに引き伸ばす (アスペクト比は無視)
+    Scale(u32, u32),
+    /// 幅を指定値に合わせ、高さはアスペクト比を保って調整
+    FitWidth(u32),
+    /// 高さを指定値に合わせ、幅はアスペクト比を保って調整
+    FitHeight(u32),
+    /// 指定した箱に収まるように縮小 (アスペクト比を保つ、はみ出さない)
+    Fit(u32, u32),
+    /// 指定したサイズを過不足なく埋めるようにクロップする
+    Fill(u32, u32),
+}
+
+impl FromStr for ResizeOp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, rest) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid resize spec '{}', expected KIND:VALUE", s))?;
+
+        let parse_dims = |rest: &str| -> Result<(u32, u32), String> {
+            let (w, h) = rest
+                .split_once('x')
+                .ok_or_else(|| format!("Invalid dimensions '{}', expected WxH", rest))?;
+            let w: u32 = w
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid width: '{}'", w))?;
+            let h: u32 = h
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid height: '{}'", h))?;
+            Ok((w, h))
+        };
+
+        match kind.trim().to_lowercase().as_str() {
+            "scale" => {
+                let (w, h) = parse_dims(rest)?;
+                Ok(ResizeOp::Scale(w, h))
+            }
+            "fit-width" => {
+                let w: u32 = rest
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid width: '{}'", rest))?;
+                Ok(ResizeOp::FitWidth(w))
+            }
+            "fit-height" => {
+                let h: u32 = rest
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid height: '{}'", rest))?;
+                Ok(ResizeOp::FitHeight(h))
+            }
+            "fit" => {
+                let (w, h) = parse_dims(rest)?;
+                Ok(ResizeOp::Fit(w, h))
+            }
+            "fill" => {
+                let (w, h) = parse_dims(rest)?;
+                Ok(ResizeOp::Fill(w, h))
+            }
+            other => Err(format!(
+                "Unknown resize kind '{}', expected one of: scale, fit-width, fit-height, fit, fill",
+                other
+            )),
+        }
+    }
+}
+
+impl ResizeOp {
+    /// 画像に操作を適用する
+    pub fn apply(&self, img: DynamicImage) -> DynamicImage {
+        match *self {
+            ResizeOp::Scale(w, h) => img.resize_exact(w, h, FilterType::Lanczos3),
+            ResizeOp::FitWidth(w) => {
+                let (width, height) = img.dimensions();
+                let h = (height as f64 * (w as f64 / width as f64)).round() as u32;
+                img.resize_exact(w, h.max(1), FilterType::Lanczos3)
+            }
+            ResizeOp::FitHeight(h) => {
+                let (width, height) = img.dimensions();
+                let w = (width as f64 * (h as f64 / height as f64)).round() as u32;
+                img.resize_exact(w.max(1), h, FilterType::Lanczos3)
+            }
+            ResizeOp::Fit(w, h) => {
+                let (width, height) = img.dimensions();
+                if width <= w && height <= h {
+                    img
+                } else {
+                    img.resize(w, h, FilterType::Lanczos3)
+                }
+            }
+            ResizeOp::Fill(w, h) => img.resize_to_fill(w, h, FilterType::Lanczos3),
+        }
+    }
+}
+
+/// 指定したアスペクト比に変換 (中央クロップ)
+pub fn convert_aspect_ratio_crop(img: DynamicImage, aspect_ratio: AspectRatio) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let target_ratio = aspect_ratio.ratio();
+    let current_ratio = width as f64 / height as f64;
+
+    if (current_ratio - target_ratio).abs() < 0.001 {
+        // 既に目標比の場合はそのまま
+        return img;
+    }
+
+    let (crop_width, crop_height) = if current_ratio > target_ratio {
+        // 横長すぎる → 幅を削る
+        let new_width = (height as f64 * target_ratio).round() as u32;
+        (new_width, height)
+    } else {
+        // 縦長すぎる → 高さを削る
+        let new_height = (width as f64 / target_ratio).round() as u32;
+        (width, new_height)
+    };
+
+    let x = (width.saturating_sub(crop_width)) / 2;
+    let y = (height.saturating_sub(crop_height)) / 2;
+
+    img.crop_imm(x, y, crop_width, crop_height)
+}
+
+/// 指定したアスペクト比に変換 (パディング)
+pub fn convert_aspect_ratio_pad(
+    img: DynamicImage,
+    aspect_ratio: AspectRatio,
+    bg_color: BackgroundColor,
+) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let target_ratio = aspect_ratio.ratio();
+    let current_ratio = width as f64 / height as f64;
+
+    if (current_ratio - target_ratio).abs() < 0.001 {
+        // 既に目標比の場合はそのまま
+        return img;
+    }
+
+    let (new_width, new_height) = if current_ratio > target_ratio {
+        // 横長すぎる → 上下にパディング
+        let new_height = (width as f64 / target_ratio).round() as u32;
+        (width, new_height)
+    } else {
+        // 縦長すぎる → 左右にパディング
+        let new_width = (height as f64 * target_ratio).round() as u32;
+        (new_width, height)
+    };
+
+    let mut canvas = RgbaImage::from_pixel(new_width, new_height, bg_color.to_rgba());
+
+    let x = (new_width.saturating_sub(width)) / 2;
+    let y = (new_height.saturating_sub(height)) / 2;
+
+    image::imageops::overlay(&mut canvas, &img.to_rgba8(), x.into(), y.into());
+
+    DynamicImage::ImageRgba8(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aspect_ratio_parses_w_h() {
+        let ar = AspectRatio::from_str("4:5").unwrap();
+        assert_eq!((ar.w, ar.h), (4, 5));
+    }
+
+    #[test]
+    fn aspect_ratio_rejects_missing_colon() {
+        assert!(AspectRatio::from_str("45").is_err());
+    }
+
+    #[test]
+    fn aspect_ratio_rejects_zero_components() {
+        assert!(AspectRatio::from_str("0:5").is_err());
+        assert!(AspectRatio::from_str("5:0").is_err());
+    }
+
+    #[test]
+    fn resize_op_parses_each_kind() {
+        assert!(matches!(
+            ResizeOp::from_str("scale:800x600").unwrap(),
+            ResizeOp::Scale(800, 600)
+        ));
+        assert!(matches!(
+            ResizeOp::from_str("fit-width:1024").unwrap(),
+            ResizeOp::FitWidth(1024)
+        ));
+        assert!(matches!(
+            ResizeOp::from_str("fit-height:768").unwrap(),
+            ResizeOp::FitHeight(768)
+        ));
+        assert!(matches!(
+            ResizeOp::from_str("fit:2048x2048").unwrap(),
+            ResizeOp::Fit(2048, 2048)
+        ));
+        assert!(matches!(
+            ResizeOp::from_str("fill:500x500").unwrap(),
+            ResizeOp::Fill(500, 500)
+        ));
+    }
+
+    #[test]
+    fn resize_op_rejects_unknown_kind() {
+        assert!(ResizeOp::from_str("zoom:100x100").is_err());
+    }
+
+    #[test]
+    fn resize_op_rejects_malformed_dims() {
+        assert!(ResizeOp::from_str("scale:800").is_err());
+    }
+}